@@ -0,0 +1,334 @@
+//! A small interpreter that executes translated REIL instructions.
+//!
+//! Where the rest of the crate only *translates* machine code into REIL, this
+//! module *runs* the resulting instruction stream over an explicit machine
+//! state, turning the translator into a simple analysis engine. It is the REIL
+//! counterpart of the tiny bytecode VMs that usually accompany a disassembler.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use openreil_sys::root::{reil_arg_t, reil_inst_t, reil_size_t};
+
+use {ReilArg, ReilArgType, ReilInst, ReilOp};
+
+/// Error raised while evaluating a REIL instruction.
+///
+/// Evaluation never panics on recoverable conditions such as a division by
+/// zero; those are surfaced as an `Err` so callers can react instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// A `DIV`/`MOD`/`SDIV`/`SMOD` instruction had a zero divisor.
+    DivisionByZero,
+    /// The opcode is not executable (e.g. `I_UNK`).
+    UnsupportedOp(ReilOp),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::UnsupportedOp(op) => write!(f, "unsupported opcode: {:?}", op),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Width in bits of a `reil_size_t`.
+fn size_in_bits(size: reil_size_t) -> u32 {
+    match size {
+        reil_size_t::U1 => 1,
+        reil_size_t::U8 => 8,
+        reil_size_t::U16 => 16,
+        reil_size_t::U32 => 32,
+        reil_size_t::U64 => 64,
+    }
+}
+
+/// Number of byte-addressed cells occupied by an operand of the given size.
+fn byte_count(size: reil_size_t) -> u64 {
+    ((size_in_bits(size) + 7) / 8).max(1) as u64
+}
+
+/// Mask `val` down to the low `size` bits.
+fn mask(size: reil_size_t, val: u64) -> u64 {
+    let bits = size_in_bits(size);
+    if bits >= 64 {
+        val
+    } else {
+        val & ((1u64 << bits) - 1)
+    }
+}
+
+/// Shift `a` by `count` bits against the *destination* width.
+///
+/// Rust's `wrapping_shl`/`wrapping_shr` reduce the shift count modulo the 64-bit
+/// operand width, which is wrong for narrower destinations: a shift by exactly
+/// the destination width (or more) must clear the value rather than wrap back to
+/// a smaller shift. The eventual result is still masked down to `size` by the
+/// caller.
+fn shift(a: u64, count: u64, size: reil_size_t, left: bool) -> u64 {
+    let width = size_in_bits(size) as u64;
+    if count >= width {
+        0
+    } else if left {
+        a << count
+    } else {
+        a >> count
+    }
+}
+
+/// Interpret the low `size` bits of `val` as a two's-complement signed integer.
+fn sign_extend(val: u64, size: reil_size_t) -> i64 {
+    let bits = size_in_bits(size);
+    if bits >= 64 {
+        val as i64
+    } else {
+        let shift = 64 - bits;
+        ((val << shift) as i64) >> shift
+    }
+}
+
+/// The machine state a REIL instruction stream executes against.
+///
+/// Registers and temporaries share a single name-keyed map, memory is a sparse
+/// byte-addressed map and `pc` tracks the `(raw_address, reil_offset)` ordering
+/// produced by [`ReilInst::address`].
+#[derive(Debug, Clone, Default)]
+pub struct ReilState {
+    /// Architecture registers and REIL temporaries, keyed by name.
+    pub regs: HashMap<String, u64>,
+    /// Sparse, byte-addressed memory.
+    pub mem: HashMap<u64, u8>,
+    /// Program counter over the encoded `address()` ordering.
+    pub pc: u64,
+}
+
+impl ReilState {
+    /// Create an empty state with a zeroed program counter.
+    pub fn new() -> Self {
+        ReilState::default()
+    }
+
+    /// Read the current value of a source operand.
+    ///
+    /// Constants and locations yield their literal value; registers and
+    /// temporaries are looked up by name, defaulting to zero when unset.
+    fn read(&self, arg: &reil_arg_t) -> u64 {
+        match arg.arg_type() {
+            ReilArgType::Const | ReilArgType::Loc => arg.val().unwrap_or(0),
+            ReilArgType::Reg | ReilArgType::Temp => arg
+                .name()
+                .and_then(|name| self.regs.get(&name).copied())
+                .unwrap_or(0),
+            ReilArgType::None => 0,
+        }
+    }
+
+    /// Write `value`, masked to the operand's size, into the destination register.
+    fn write(&mut self, arg: &reil_arg_t, value: u64) {
+        if let Some(name) = arg.name() {
+            self.regs.insert(name, mask(arg.size(), value));
+        }
+    }
+
+    /// Load `byte_count(size)` little-endian bytes starting at `addr`.
+    fn load(&self, addr: u64, size: reil_size_t) -> u64 {
+        let mut value = 0u64;
+        for i in 0..byte_count(size) {
+            let byte = self.mem.get(&(addr.wrapping_add(i))).copied().unwrap_or(0);
+            value |= (byte as u64) << (8 * i);
+        }
+        value
+    }
+
+    /// Store the low bytes of `value` little-endian starting at `addr`.
+    fn store(&mut self, addr: u64, value: u64, size: reil_size_t) {
+        for i in 0..byte_count(size) {
+            let byte = ((value >> (8 * i)) & 0xff) as u8;
+            self.mem.insert(addr.wrapping_add(i), byte);
+        }
+    }
+
+    /// Execute a single REIL instruction against this state.
+    ///
+    /// The program counter is advanced to the instruction's own address and,
+    /// for a taken `I_JCC`, redirected to the branch target.
+    pub fn eval(&mut self, inst: &reil_inst_t) -> Result<(), EvalError> {
+        self.pc = inst.address();
+
+        let a = self.read(&inst.a);
+        let b = self.read(&inst.b);
+        let dst_size = inst.c.size();
+
+        let result = match inst.opcode() {
+            ReilOp::None => return Ok(()),
+            ReilOp::Unknown => return Err(EvalError::UnsupportedOp(ReilOp::Unknown)),
+
+            ReilOp::Str => a,
+            ReilOp::Add => a.wrapping_add(b),
+            ReilOp::Sub => a.wrapping_sub(b),
+            ReilOp::Neg => 0u64.wrapping_sub(a),
+            ReilOp::Mul => a.wrapping_mul(b),
+            ReilOp::Div => {
+                if b == 0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                a / b
+            }
+            ReilOp::Mod => {
+                if b == 0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                a % b
+            }
+            ReilOp::Smul => {
+                (sign_extend(a, inst.a.size()).wrapping_mul(sign_extend(b, inst.b.size()))) as u64
+            }
+            ReilOp::Sdiv => {
+                if b == 0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                (sign_extend(a, inst.a.size()).wrapping_div(sign_extend(b, inst.b.size()))) as u64
+            }
+            ReilOp::Smod => {
+                if b == 0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                (sign_extend(a, inst.a.size()).wrapping_rem(sign_extend(b, inst.b.size()))) as u64
+            }
+
+            ReilOp::And => a & b,
+            ReilOp::Or => a | b,
+            ReilOp::Xor => a ^ b,
+            ReilOp::Not => !a,
+            ReilOp::Shl => shift(a, b, dst_size, true),
+            ReilOp::Shr => shift(a, b, dst_size, false),
+
+            ReilOp::Eq => (a == b) as u64,
+            ReilOp::Lt => (a < b) as u64,
+
+            ReilOp::Ldm => {
+                let value = self.load(a, dst_size);
+                self.write(&inst.c, value);
+                return Ok(());
+            }
+            ReilOp::Stm => {
+                let addr = self.read(&inst.c);
+                self.store(addr, a, inst.a.size());
+                return Ok(());
+            }
+            ReilOp::Jcc => {
+                if a != 0 {
+                    // `inst.c` is an `A_LOC` whose raw value is a plain machine
+                    // address, whereas `pc` is encoded as `raw_addr << 8 | offset`
+                    // by `address()`. Normalise the target into that same space
+                    // (offset 0) so a post-jump `pc` is comparable with the
+                    // `address()` of other instructions.
+                    self.pc = self.read(&inst.c) << 8;
+                }
+                return Ok(());
+            }
+        };
+
+        self.write(&inst.c, result);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    use openreil_sys::root::{reil_op_t, reil_type_t};
+
+    /// Build a constant operand carrying `val` with the given size.
+    fn constant(val: u64, size: reil_size_t) -> reil_arg_t {
+        let mut arg: reil_arg_t = unsafe { mem::zeroed() };
+        arg.type_ = reil_type_t::A_CONST;
+        arg.size = size;
+        arg.val = val as _;
+        arg
+    }
+
+    /// Build a register/temporary operand named `name`.
+    fn reg(name: &str, size: reil_size_t) -> reil_arg_t {
+        let mut arg: reil_arg_t = unsafe { mem::zeroed() };
+        arg.type_ = reil_type_t::A_REG;
+        arg.size = size;
+        for (slot, byte) in arg.name.iter_mut().zip(name.bytes()) {
+            *slot = byte as _;
+        }
+        arg
+    }
+
+    /// Assemble an instruction from an opcode and its three operands.
+    fn inst(op: reil_op_t, a: reil_arg_t, b: reil_arg_t, c: reil_arg_t) -> reil_inst_t {
+        let mut inst: reil_inst_t = unsafe { mem::zeroed() };
+        inst.op = op;
+        inst.a = a;
+        inst.b = b;
+        inst.c = c;
+        inst
+    }
+
+    #[test]
+    fn mask_truncates_to_size() {
+        assert_eq!(mask(reil_size_t::U8, 0x1ff), 0xff);
+        assert_eq!(mask(reil_size_t::U1, 0b10), 0);
+        assert_eq!(mask(reil_size_t::U64, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn sign_extend_recovers_negative_values() {
+        assert_eq!(sign_extend(0xff, reil_size_t::U8), -1);
+        assert_eq!(sign_extend(0x7f, reil_size_t::U8), 127);
+        assert_eq!(sign_extend(0x8000, reil_size_t::U16), -32768);
+    }
+
+    #[test]
+    fn shift_by_width_clears_value() {
+        // A 64-bit left shift by 64 must yield 0, not the unchanged operand.
+        assert_eq!(shift(0xdead, 64, reil_size_t::U64, true), 0);
+        assert_eq!(shift(0xff, 8, reil_size_t::U8, false), 0);
+        assert_eq!(shift(1, 3, reil_size_t::U32, true), 8);
+    }
+
+    #[test]
+    fn destination_write_is_masked() {
+        let mut state = ReilState::new();
+        // 0xff + 0x01 = 0x100, truncated to the 8-bit destination -> 0x00.
+        let i = inst(
+            reil_op_t::I_ADD,
+            constant(0xff, reil_size_t::U8),
+            constant(0x01, reil_size_t::U8),
+            reg("R_AL", reil_size_t::U8),
+        );
+        state.eval(&i).unwrap();
+        assert_eq!(state.regs.get("R_AL"), Some(&0x00));
+    }
+
+    #[test]
+    fn division_by_zero_is_recoverable() {
+        let mut state = ReilState::new();
+        let i = inst(
+            reil_op_t::I_DIV,
+            constant(10, reil_size_t::U32),
+            constant(0, reil_size_t::U32),
+            reg("R_EAX", reil_size_t::U32),
+        );
+        assert_eq!(state.eval(&i), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn memory_store_load_round_trip() {
+        let mut state = ReilState::new();
+        state.store(0x1000, 0xdeadbeef, reil_size_t::U32);
+        assert_eq!(state.load(0x1000, reil_size_t::U32), 0xdeadbeef);
+        // Stored little-endian, so the low byte lands at the base address.
+        assert_eq!(state.mem.get(&0x1000), Some(&0xef));
+        assert_eq!(state.load(0x1000, reil_size_t::U8), 0xef);
+    }
+}
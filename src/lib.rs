@@ -3,6 +3,8 @@
 extern crate openreil_sys;
 extern crate libc;
 
+pub mod eval;
+
 use openreil_sys::root::{reil_addr_t, reil_inst_t, reil_t, reil_arch_t, reil_inst_print,
                             reil_inst_handler_t, reil_init, reil_close, reil_translate,
                             reil_translate_insn};
@@ -11,6 +13,8 @@ pub use openreil_sys::root::{reil_op_t, reil_type_t, reil_size_t, reil_arg_t, re
 use std::mem;
 use std::ops::Drop;
 use std::marker;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
 
 
 /// Specifies the architecture of the binary code.
@@ -22,6 +26,159 @@ pub enum ReilArch {
     ARM,
 }
 
+/// A safe, idiomatic Rust mirror of the bindgen-generated `reil_op_t`.
+///
+/// Using this enum on the public surface gives callers exhaustiveness checking,
+/// documentation and a stable classification (via [`ReilOp::is_memory`] and
+/// friends) instead of forcing them to match on the raw C enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReilOp {
+    None,
+    Unknown,
+    Jcc,
+    Str,
+    Stm,
+    Ldm,
+    Add,
+    Sub,
+    Neg,
+    Mul,
+    Div,
+    Mod,
+    Smul,
+    Sdiv,
+    Smod,
+    Shl,
+    Shr,
+    And,
+    Or,
+    Xor,
+    Not,
+    Eq,
+    Lt,
+}
+
+impl ReilOp {
+    /// Whether this is an arithmetic operation (signed or unsigned).
+    pub fn is_arithmetic(self) -> bool {
+        use ReilOp::*;
+        matches!(
+            self,
+            Add | Sub | Neg | Mul | Div | Mod | Smul | Sdiv | Smod
+        )
+    }
+
+    /// Whether this is a bitwise operation.
+    pub fn is_bitwise(self) -> bool {
+        use ReilOp::*;
+        matches!(self, And | Or | Xor | Not | Shl | Shr)
+    }
+
+    /// Whether this is a memory access (`LDM`/`STM`).
+    pub fn is_memory(self) -> bool {
+        matches!(self, ReilOp::Ldm | ReilOp::Stm)
+    }
+
+    /// Whether this transfers control (`JCC`).
+    pub fn is_branch(self) -> bool {
+        matches!(self, ReilOp::Jcc)
+    }
+}
+
+impl From<reil_op_t> for ReilOp {
+    fn from(op: reil_op_t) -> Self {
+        match op {
+            reil_op_t::I_NONE => ReilOp::None,
+            reil_op_t::I_UNK => ReilOp::Unknown,
+            reil_op_t::I_JCC => ReilOp::Jcc,
+            reil_op_t::I_STR => ReilOp::Str,
+            reil_op_t::I_STM => ReilOp::Stm,
+            reil_op_t::I_LDM => ReilOp::Ldm,
+            reil_op_t::I_ADD => ReilOp::Add,
+            reil_op_t::I_SUB => ReilOp::Sub,
+            reil_op_t::I_NEG => ReilOp::Neg,
+            reil_op_t::I_MUL => ReilOp::Mul,
+            reil_op_t::I_DIV => ReilOp::Div,
+            reil_op_t::I_MOD => ReilOp::Mod,
+            reil_op_t::I_SMUL => ReilOp::Smul,
+            reil_op_t::I_SDIV => ReilOp::Sdiv,
+            reil_op_t::I_SMOD => ReilOp::Smod,
+            reil_op_t::I_SHL => ReilOp::Shl,
+            reil_op_t::I_SHR => ReilOp::Shr,
+            reil_op_t::I_AND => ReilOp::And,
+            reil_op_t::I_OR => ReilOp::Or,
+            reil_op_t::I_XOR => ReilOp::Xor,
+            reil_op_t::I_NOT => ReilOp::Not,
+            reil_op_t::I_EQ => ReilOp::Eq,
+            reil_op_t::I_LT => ReilOp::Lt,
+        }
+    }
+}
+
+impl From<ReilOp> for reil_op_t {
+    fn from(op: ReilOp) -> Self {
+        match op {
+            ReilOp::None => reil_op_t::I_NONE,
+            ReilOp::Unknown => reil_op_t::I_UNK,
+            ReilOp::Jcc => reil_op_t::I_JCC,
+            ReilOp::Str => reil_op_t::I_STR,
+            ReilOp::Stm => reil_op_t::I_STM,
+            ReilOp::Ldm => reil_op_t::I_LDM,
+            ReilOp::Add => reil_op_t::I_ADD,
+            ReilOp::Sub => reil_op_t::I_SUB,
+            ReilOp::Neg => reil_op_t::I_NEG,
+            ReilOp::Mul => reil_op_t::I_MUL,
+            ReilOp::Div => reil_op_t::I_DIV,
+            ReilOp::Mod => reil_op_t::I_MOD,
+            ReilOp::Smul => reil_op_t::I_SMUL,
+            ReilOp::Sdiv => reil_op_t::I_SDIV,
+            ReilOp::Smod => reil_op_t::I_SMOD,
+            ReilOp::Shl => reil_op_t::I_SHL,
+            ReilOp::Shr => reil_op_t::I_SHR,
+            ReilOp::And => reil_op_t::I_AND,
+            ReilOp::Or => reil_op_t::I_OR,
+            ReilOp::Xor => reil_op_t::I_XOR,
+            ReilOp::Not => reil_op_t::I_NOT,
+            ReilOp::Eq => reil_op_t::I_EQ,
+            ReilOp::Lt => reil_op_t::I_LT,
+        }
+    }
+}
+
+/// A safe, idiomatic Rust mirror of the bindgen-generated `reil_type_t`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReilArgType {
+    None,
+    Reg,
+    Temp,
+    Const,
+    Loc,
+}
+
+impl From<reil_type_t> for ReilArgType {
+    fn from(ty: reil_type_t) -> Self {
+        match ty {
+            reil_type_t::A_NONE => ReilArgType::None,
+            reil_type_t::A_REG => ReilArgType::Reg,
+            reil_type_t::A_TEMP => ReilArgType::Temp,
+            reil_type_t::A_CONST => ReilArgType::Const,
+            reil_type_t::A_LOC => ReilArgType::Loc,
+        }
+    }
+}
+
+impl From<ReilArgType> for reil_type_t {
+    fn from(ty: ReilArgType) -> Self {
+        match ty {
+            ReilArgType::None => reil_type_t::A_NONE,
+            ReilArgType::Reg => reil_type_t::A_REG,
+            ReilArgType::Temp => reil_type_t::A_TEMP,
+            ReilArgType::Const => reil_type_t::A_CONST,
+            ReilArgType::Loc => reil_type_t::A_LOC,
+        }
+    }
+}
+
 /// Callback handler type to further process the resulting REIL instructions.
 /// Is a type alias for a C function.
 ///
@@ -31,6 +188,32 @@ pub type ReilInstHandler<T> = extern "C" fn(*mut ReilRawInst, *mut T) -> i32;
 /// A raw REIL instruction, that is a simple autogenerated wrapper for the original C type.
 pub type ReilRawInst = reil_inst_t;
 
+/// Error returned when a translation request fails.
+///
+/// `reil_translate`/`reil_translate_insn` return the number of REIL
+/// instructions generated, and a non-positive count for non-empty input means
+/// the bytes could not be decoded. The start address and byte length of the
+/// offending request are carried along for diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReilError {
+    /// Address the failed translation started at.
+    pub start_address: u32,
+    /// Length in bytes of the buffer that could not be translated.
+    pub length: usize,
+}
+
+impl fmt::Display for ReilError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to translate {} byte(s) at address {:#x}",
+            self.length, self.start_address
+        )
+    }
+}
+
+impl std::error::Error for ReilError {}
+
 /// A disassembler object.
 ///
 /// The `Reil` type provides a simple interface to disassemble and translate single or multiple instructions
@@ -39,9 +222,36 @@ pub type ReilRawInst = reil_inst_t;
 /// A `handler` callback function can be provided during construction to further process the resulting REIL instructions.
 pub struct Reil<'a, T: 'a> {
     reil_handle: reil_t,
+    arch: ReilArch,
+    _handler: Option<Box<Box<dyn FnMut(&ReilRawInst) -> i32 + 'a>>>,
     _marker: marker::PhantomData<&'a mut T>,
 }
 
+/// Return code handed back to the C library when a closure handler panics.
+///
+/// Unwinding across the `extern "C"` boundary is undefined behaviour, so a
+/// panicking closure is caught and reported to `libopenreil` as a plain error.
+const HANDLER_PANIC: libc::c_int = -1;
+
+/// Monomorphized trampoline registered with `libopenreil` by [`Reil::with_handler`].
+///
+/// It recovers the boxed closure stored in the context slot and forwards the
+/// instruction to it, making sure that a panic inside the closure cannot unwind
+/// into the C caller.
+extern "C" fn closure_trampoline(inst: *mut ReilRawInst, context: *mut libc::c_void) -> libc::c_int {
+    if inst.is_null() || context.is_null() {
+        return HANDLER_PANIC;
+    }
+
+    let handler = unsafe { &mut *(context as *mut Box<dyn FnMut(&ReilRawInst) -> i32>) };
+    let inst = unsafe { &*inst };
+
+    match panic::catch_unwind(AssertUnwindSafe(|| handler(inst))) {
+        Ok(code) => code as libc::c_int,
+        Err(_) => HANDLER_PANIC,
+    }
+}
+
 impl<'a, T: 'a> Reil<'a, T> {
     /// Construct a new disassembler object
     /// The handler function can be used to process the resulting REIL instructions
@@ -51,7 +261,7 @@ impl<'a, T: 'a> Reil<'a, T> {
         handler: Option<ReilInstHandler<T>>,
         context: &'a mut T,
     ) -> Option<Self> {
-        let arch = match arch {
+        let reil_arch = match arch {
             ReilArch::X86 => reil_arch_t::ARCH_X86,
             ReilArch::ARM => reil_arch_t::ARCH_ARM,
         };
@@ -59,7 +269,7 @@ impl<'a, T: 'a> Reil<'a, T> {
 
         let c_ptr = context as *mut _;
 
-        let reil = unsafe { reil_init(arch, handler, c_ptr as *mut libc::c_void) };
+        let reil = unsafe { reil_init(reil_arch, handler, c_ptr as *mut libc::c_void) };
 
         if reil.is_null() {
             return None;
@@ -67,6 +277,8 @@ impl<'a, T: 'a> Reil<'a, T> {
 
         let new_reil = Reil {
             reil_handle: reil,
+            arch,
+            _handler: None,
             _marker: marker::PhantomData,
         };
 
@@ -75,30 +287,135 @@ impl<'a, T: 'a> Reil<'a, T> {
 
     /// Translate the binary data given in `data` to REIL instructions,
     /// `start_address` designates the starting address the decoded instructions get assigned.
-    pub fn translate(&mut self, data: &mut [u8], start_address: u32) {
-        unsafe {
+    ///
+    /// Returns the number of REIL instructions generated on success, or a
+    /// [`ReilError`] if the bytes could not be decoded.
+    pub fn translate(&mut self, data: &mut [u8], start_address: u32) -> Result<usize, ReilError> {
+        let count = unsafe {
             reil_translate(
                 self.reil_handle,
                 start_address as reil_addr_t,
                 data.as_mut_ptr(),
                 data.len() as libc::c_int,
-            );
+            )
+        };
+
+        if !data.is_empty() && count <= 0 {
+            Err(ReilError {
+                start_address,
+                length: data.len(),
+            })
+        } else {
+            Ok(count.max(0) as usize)
         }
     }
 
     /// Translate a single instruction from the binary data given in `data` and start addressing at the given address.
-    pub fn translate_instruction(&mut self, data: &mut [u8], start_address: u32) {
-        unsafe {
+    ///
+    /// Returns the number of REIL instructions generated on success, or a
+    /// [`ReilError`] if the instruction could not be decoded.
+    pub fn translate_instruction(
+        &mut self,
+        data: &mut [u8],
+        start_address: u32,
+    ) -> Result<usize, ReilError> {
+        let count = unsafe {
             reil_translate_insn(
                 self.reil_handle,
                 start_address as reil_addr_t,
                 data.as_mut_ptr(),
                 data.len() as libc::c_int,
-            );
+            )
+        };
+
+        if !data.is_empty() && count <= 0 {
+            Err(ReilError {
+                start_address,
+                length: data.len(),
+            })
+        } else {
+            Ok(count.max(0) as usize)
         }
     }
 }
 
+impl<'a> Reil<'a, ()> {
+    /// Construct a new disassembler object driven by a safe Rust closure.
+    ///
+    /// The closure is invoked once per translated REIL instruction and its
+    /// return value is forwarded to `libopenreil` unchanged. Contrary to
+    /// [`Reil::new`] this requires no `unsafe` trampoline on the caller's side:
+    /// the closure is boxed, stashed in the context slot and recovered by a
+    /// single `extern "C"` thunk. A panic escaping the closure is caught and
+    /// reported as an error code instead of unwinding across the C boundary.
+    pub fn with_handler<F>(arch: ReilArch, handler: F) -> Option<Self>
+    where
+        F: FnMut(&ReilRawInst) -> i32 + 'a,
+    {
+        let reil_arch = match arch {
+            ReilArch::X86 => reil_arch_t::ARCH_X86,
+            ReilArch::ARM => reil_arch_t::ARCH_ARM,
+        };
+
+        let mut handler: Box<Box<dyn FnMut(&ReilRawInst) -> i32 + 'a>> =
+            Box::new(Box::new(handler));
+        let context = &mut *handler as *mut Box<dyn FnMut(&ReilRawInst) -> i32 + 'a>;
+
+        let reil = unsafe {
+            reil_init(reil_arch, Some(closure_trampoline), context as *mut libc::c_void)
+        };
+
+        if reil.is_null() {
+            return None;
+        }
+
+        Some(Reil {
+            reil_handle: reil,
+            arch,
+            _handler: Some(handler),
+            _marker: marker::PhantomData,
+        })
+    }
+
+    /// Translate `data` and collect the resulting REIL instructions into a `Vec`.
+    ///
+    /// This installs a handler that copies every delivered `reil_inst_t` by value
+    /// into a vector and returns the collected instructions, so callers can
+    /// inspect the result without writing an `unsafe` callback themselves. It is
+    /// an associated function rather than a method because the collecting
+    /// handler has to be registered at [`reil_init`] time; a decode failure is
+    /// surfaced as a [`ReilError`] instead of being indistinguishable from an
+    /// empty instruction stream.
+    pub fn translate_to_vec(
+        arch: ReilArch,
+        data: &mut [u8],
+        start_address: u32,
+    ) -> Result<Vec<ReilRawInst>, ReilError> {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let collected: Rc<RefCell<Vec<ReilRawInst>>> = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&collected);
+
+        let mut reil = match Reil::with_handler(arch, move |inst: &ReilRawInst| {
+            sink.borrow_mut().push(*inst);
+            0
+        }) {
+            Some(reil) => reil,
+            None => return Ok(Vec::new()),
+        };
+
+        reil.translate(data, start_address)?;
+        // Drop the disassembler first so its boxed handler releases the other
+        // `Rc` handle to `collected`, leaving us the sole owner to unwrap.
+        drop(reil);
+
+        Ok(Rc::try_unwrap(collected)
+            .expect("handler outlived translation")
+            .into_inner())
+    }
+}
+
 impl<'a, T: 'a> Drop for Reil<'a, T> {
     fn drop(&mut self) {
         unsafe {
@@ -116,7 +433,13 @@ pub trait ReilInst {
     fn first_operand(&self) -> Option<reil_arg_t>;
     fn second_operand(&self) -> Option<reil_arg_t>;
     fn third_operand(&self) -> Option<reil_arg_t>;
-    fn opcode(&self) -> reil_op_t;
+    fn opcode(&self) -> ReilOp;
+    /// Return a wrapper implementing [`std::fmt::Display`] for this instruction.
+    ///
+    /// Unlike [`ReilInst::print`] the rendering happens entirely in Rust, so the
+    /// result can be captured into a `String`, logged or snapshot-tested without
+    /// going through the C library's stdout writer.
+    fn display(&self) -> ReilInstDisplay<'_>;
 }
 
 impl ReilInst for reil_inst_t {
@@ -160,21 +483,27 @@ impl ReilInst for reil_inst_t {
         }
     }
 
-    fn opcode(&self) -> reil_op_t {
-        self.op
+    fn opcode(&self) -> ReilOp {
+        ReilOp::from(self.op)
+    }
+
+    fn display(&self) -> ReilInstDisplay<'_> {
+        ReilInstDisplay(self)
     }
 }
 
 pub trait ReilArg {
-    fn arg_type(&self) -> reil_type_t;
+    fn arg_type(&self) -> ReilArgType;
     fn size(&self) -> reil_size_t;
     fn val(&self) -> Option<u64>;
     fn name(&self) -> Option<String>;
+    /// Return a wrapper implementing [`std::fmt::Display`] for this operand.
+    fn display(&self) -> ReilArgDisplay<'_>;
 }
 
 impl ReilArg for reil_arg_t {
-    fn arg_type(&self) -> reil_type_t {
-        self.type_
+    fn arg_type(&self) -> ReilArgType {
+        ReilArgType::from(self.type_)
     }
 
     fn size(&self) -> reil_size_t {
@@ -183,13 +512,13 @@ impl ReilArg for reil_arg_t {
 
     fn val(&self) -> Option<u64> {
         match self.arg_type() {
-            reil_type_t::A_CONST | reil_type_t::A_LOC => Some(self.val as u64),
+            ReilArgType::Const | ReilArgType::Loc => Some(self.val as u64),
             _ => None,
         }
     }
 
     fn name(&self) -> Option<String> {
-        if self.arg_type() == reil_type_t::A_NONE {
+        if self.arg_type() == ReilArgType::None {
             return None;
         }
         let chars = self.name.iter()
@@ -199,4 +528,156 @@ impl ReilArg for reil_arg_t {
 
         String::from_utf8(chars).ok()
     }
+
+    fn display(&self) -> ReilArgDisplay<'_> {
+        ReilArgDisplay(self)
+    }
+}
+
+/// Mnemonic for a REIL opcode, matching the spelling used by `libopenreil`.
+fn op_mnemonic(op: ReilOp) -> &'static str {
+    match op {
+        ReilOp::None => "NONE",
+        ReilOp::Unknown => "UNK",
+        ReilOp::Jcc => "JCC",
+        ReilOp::Str => "STR",
+        ReilOp::Stm => "STM",
+        ReilOp::Ldm => "LDM",
+        ReilOp::Add => "ADD",
+        ReilOp::Sub => "SUB",
+        ReilOp::Neg => "NEG",
+        ReilOp::Mul => "MUL",
+        ReilOp::Div => "DIV",
+        ReilOp::Mod => "MOD",
+        ReilOp::Smul => "SMUL",
+        ReilOp::Sdiv => "SDIV",
+        ReilOp::Smod => "SMOD",
+        ReilOp::Shl => "SHL",
+        ReilOp::Shr => "SHR",
+        ReilOp::And => "AND",
+        ReilOp::Or => "OR",
+        ReilOp::Xor => "XOR",
+        ReilOp::Not => "NOT",
+        ReilOp::Eq => "EQ",
+        ReilOp::Lt => "LT",
+    }
+}
+
+/// Width in bits of a `reil_size_t`, used for the `:size` operand suffix.
+fn size_bits(size: reil_size_t) -> u16 {
+    match size {
+        reil_size_t::U1 => 1,
+        reil_size_t::U8 => 8,
+        reil_size_t::U16 => 16,
+        reil_size_t::U32 => 32,
+        reil_size_t::U64 => 64,
+    }
+}
+
+/// [`Display`](std::fmt::Display) adapter for a single REIL operand.
+///
+/// Returned by [`ReilArg::display`]. Constants are printed in hex, registers and
+/// temporaries as `name:size`, jump locations as their target and `A_NONE` as an
+/// empty string.
+pub struct ReilArgDisplay<'a>(&'a reil_arg_t);
+
+impl<'a> fmt::Display for ReilArgDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arg = self.0;
+        match arg.arg_type() {
+            ReilArgType::None => Ok(()),
+            ReilArgType::Const => write!(f, "0x{:x}", arg.val().unwrap_or(0)),
+            ReilArgType::Loc => write!(f, "0x{:x}", arg.val().unwrap_or(0)),
+            ReilArgType::Reg | ReilArgType::Temp => {
+                let name = arg.name().unwrap_or_default();
+                write!(f, "{}:{}", name, size_bits(arg.size()))
+            }
+        }
+    }
+}
+
+/// [`Display`](std::fmt::Display) adapter for a whole REIL instruction.
+///
+/// Returned by [`ReilInst::display`]. The instruction address is rendered as
+/// `raw_addr.offset` and is followed by the mnemonic and the present operands.
+pub struct ReilInstDisplay<'a>(&'a reil_inst_t);
+
+impl<'a> fmt::Display for ReilInstDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inst = self.0;
+        write!(
+            f,
+            "{:08x}.{:02x} {:<4}",
+            inst.raw_address(),
+            inst.reil_offset(),
+            op_mnemonic(inst.opcode())
+        )?;
+
+        let operands = [
+            inst.first_operand(),
+            inst.second_operand(),
+            inst.third_operand(),
+        ];
+        let mut first = true;
+        for operand in operands.iter().flatten() {
+            if first {
+                write!(f, " ")?;
+                first = false;
+            } else {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", operand.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a constant operand carrying `val` with the given size.
+    fn constant(val: u64, size: reil_size_t) -> reil_arg_t {
+        let mut arg: reil_arg_t = unsafe { mem::zeroed() };
+        arg.type_ = reil_type_t::A_CONST;
+        arg.size = size;
+        arg.val = val as _;
+        arg
+    }
+
+    /// Build a register/temporary operand named `name`.
+    fn reg(name: &str, size: reil_size_t) -> reil_arg_t {
+        let mut arg: reil_arg_t = unsafe { mem::zeroed() };
+        arg.type_ = reil_type_t::A_REG;
+        arg.size = size;
+        for (slot, byte) in arg.name.iter_mut().zip(name.bytes()) {
+            *slot = byte as _;
+        }
+        arg
+    }
+
+    #[test]
+    fn operand_display_formats() {
+        assert_eq!(constant(0xff, reil_size_t::U8).display().to_string(), "0xff");
+        assert_eq!(reg("R_EAX", reil_size_t::U32).display().to_string(), "R_EAX:32");
+        let none: reil_arg_t = unsafe { mem::zeroed() };
+        assert_eq!(none.display().to_string(), "");
+    }
+
+    #[test]
+    fn instruction_display_renders_address_and_operands() {
+        let mut inst: reil_inst_t = unsafe { mem::zeroed() };
+        inst.op = reil_op_t::I_ADD;
+        inst.raw_info.addr = 0x400 as _;
+        inst.inum = 1;
+        inst.a = reg("R_EAX", reil_size_t::U32);
+        inst.b = constant(0x10, reil_size_t::U32);
+        inst.c = reg("V_01", reil_size_t::U32);
+
+        assert_eq!(
+            inst.display().to_string(),
+            "00000400.01 ADD  R_EAX:32, 0x10, V_01:32"
+        );
+    }
 }